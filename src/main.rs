@@ -1,35 +1,193 @@
 use cargo_metadata::MetadataCommand;
 use clap::Parser;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::fs;
-use std::io::{self, Write};
-use std::path::Path;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+mod transport;
+use transport::{NativeTransport, ShellTransport, Transport};
 
 const CONFIG_FILE: &str = "cargo_deploy.json";
+const DEFAULT_ARCH: &str = "aarch64-unknown-linux-gnu";
+const DEFAULT_SSH_PORT: u16 = 22;
+/// Number of trailing lines of remote output kept in memory while `--run` is streaming.
+const RUN_LOG_CAPACITY: usize = 200;
+/// How long an SSH ControlMaster keeps the multiplexed connection open after
+/// the last client disconnects, in seconds.
+const CONTROL_PERSIST_SECS: u32 = 600;
 
 #[derive(Parser)]
 struct Args {
     /// Build in release by default, unless debug is specified.
     #[arg(long)]
     debug: bool,
+
+    /// Name of the remote profile to deploy to. Falls back to the config's
+    /// `default` profile, or the only profile if just one is configured.
+    #[arg(long)]
+    remote: Option<String>,
+
+    /// After a successful deploy, launch the binary on the remote over SSH
+    /// and stream its stdout/stderr locally until it exits or is interrupted.
+    #[arg(long)]
+    run: bool,
+
+    /// Reuse a single SSH connection (via ControlMaster) across every step
+    /// instead of opening a fresh one for each ssh/scp invocation.
+    #[arg(long)]
+    multiplex: bool,
+
+    /// Install the deployed binary as a systemd user service that restarts
+    /// on failure and survives reboots, instead of just copying it over.
+    #[arg(long)]
+    install_service: bool,
+
+    /// Connect and upload over an in-process SSH2 session instead of
+    /// shelling out to `ssh`/`scp`/`rsync`/`ssh-copy-id`. `ssh-keygen` is
+    /// still run when no local key pair exists yet.
+    #[arg(long)]
+    native_ssh: bool,
+}
+
+/// Which `Transport` to use for a remote, overridable per invocation with
+/// `--native-ssh`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum TransportKind {
+    Shell,
+    Native,
+}
+
+/// A single named deploy target, e.g. one Raspberry Pi on the bench.
+///
+/// Only `name` is required in the config file. A missing `host`/`user` is
+/// prompted for; `dest` defaults to `/home/<user>/bin`, `arch` to
+/// `DEFAULT_ARCH` and `ssh_port` to 22, so a profile can be added with a
+/// couple of lines.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Remote {
+    name: String,
+    host: Option<String>,
+    user: Option<String>,
+    dest: Option<String>,
+    arch: Option<String>,
+    ssh_port: Option<u16>,
+    /// Name of the systemd user service installed by `--install-service`,
+    /// defaulting to the binary name. Reused on repeat deploys so they
+    /// restart the existing unit instead of creating a new one.
+    service_name: Option<String>,
+    /// Extra arguments appended after the binary path in `ExecStart=`.
+    service_args: Option<String>,
+    /// Defaults to the shell-out transport; set to `native` to use the
+    /// in-process SSH2 backend without `--native-ssh` every time.
+    transport: Option<TransportKind>,
+}
+
+impl Remote {
+    fn named(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            host: None,
+            user: None,
+            dest: None,
+            arch: Some(DEFAULT_ARCH.into()),
+            ssh_port: None,
+            service_name: None,
+            service_args: None,
+            transport: None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct Config {
-    target_arch: Option<String>, // The instruction set of the remote device, for cross compiling.
-    target_dest: Option<String>, // The remote folder to which the executable is copied.
-    target_name: Option<String>, // The hostname/IP of the remote device.
-    target_user: Option<String>, // The user on the remote device.
+    #[serde(default)]
+    remotes: Vec<Remote>,
+    /// Name of the profile to use when `--remote` isn't passed.
+    default: Option<String>,
 }
 
 impl Default for Config {
+    /// Seeds a single unconfigured "default" profile so a first-ever run
+    /// with no `cargo_deploy.json` and no `--remote` still resolves to a
+    /// profile and falls through to the interactive host/user prompts,
+    /// instead of leaving `resolve` with zero profiles to pick from.
     fn default() -> Self {
-        Self {
-            target_arch: Some("aarch64-unknown-linux-gnu".into()),
-            target_dest: Some("/home/raspberry/bin".into()),
-            target_name: None,
-            target_user: None,
+        Config {
+            remotes: vec![Remote::named("default")],
+            default: None,
+        }
+    }
+}
+
+/// The pre-profiles config shape, kept around only so existing
+/// `cargo_deploy.json` files migrate instead of failing to parse.
+#[derive(Deserialize, Debug)]
+struct LegacyConfig {
+    target_arch: Option<String>,
+    target_dest: Option<String>,
+    target_name: Option<String>,
+    target_user: Option<String>,
+}
+
+impl From<LegacyConfig> for Config {
+    fn from(legacy: LegacyConfig) -> Self {
+        let remote = Remote {
+            name: "default".to_string(),
+            host: legacy.target_name,
+            user: legacy.target_user,
+            dest: legacy.target_dest,
+            arch: legacy.target_arch,
+            ssh_port: None,
+            service_name: None,
+            service_args: None,
+            transport: None,
+        };
+        Config {
+            default: Some(remote.name.clone()),
+            remotes: vec![remote],
+        }
+    }
+}
+
+impl Config {
+    fn resolve<'a>(&'a mut self, wanted: Option<&str>) -> &'a mut Remote {
+        let name = wanted
+            .map(str::to_string)
+            .or_else(|| self.default.clone())
+            .or_else(|| {
+                if self.remotes.len() == 1 {
+                    Some(self.remotes[0].name.clone())
+                } else {
+                    None
+                }
+            });
+
+        match name {
+            Some(name) => {
+                if !self.remotes.iter().any(|r| r.name == name) {
+                    if wanted.is_some() {
+                        println!("No profile named '{}'; creating it.", name);
+                    }
+                    self.remotes.push(Remote::named(&name));
+                }
+                self.remotes
+                    .iter_mut()
+                    .find(|r| r.name == name)
+                    .expect("remote was just inserted if missing")
+            }
+            None => panic!(
+                "No --remote given and config has {} profiles with no `default` set. \
+                 Pass --remote <name> or set a default in {}.",
+                self.remotes.len(),
+                CONFIG_FILE
+            ),
         }
     }
 }
@@ -38,27 +196,82 @@ fn main() {
     let args = Args::parse();
     let release_mode = !args.debug;
 
+    let bin_name = detect_binary_name();
+
     let mut config = load_or_create_config();
+    let remote = config.resolve(args.remote.as_deref());
     let mut need_save = false;
+
     // Prompt for Hostname/IP if missing.
-    if config.target_name.is_none() {
+    if remote.host.is_none() {
         print!("Enter remote hostname/IP : ");
         io::stdout().flush().unwrap();
         let mut ip = String::new();
         io::stdin().read_line(&mut ip).unwrap();
-        config.target_name = Some(ip.trim().to_string());
+        remote.host = Some(ip.trim().to_string());
         need_save = true;
     }
 
-    if config.target_user.is_none() {
+    if remote.user.is_none() {
         print!("Enter remote username : ");
         io::stdout().flush().unwrap();
         let mut username = String::new();
         io::stdin().read_line(&mut username).unwrap();
         let username = username.trim().to_string();
-        config.target_user = Some(username.clone());
+        remote.user = Some(username.clone());
         // Update the home directory based on the username.
-        config.target_dest = Some(format!("/home/{}/bin", username));
+        remote.dest = Some(format!("/home/{}/bin", username));
+        need_save = true;
+    }
+
+    if remote.dest.is_none() {
+        remote.dest = Some(format!("/home/{}/bin", remote.user.as_ref().unwrap()));
+        need_save = true;
+    }
+
+    if remote.arch.is_none() {
+        remote.arch = Some(DEFAULT_ARCH.into());
+        need_save = true;
+    }
+
+    if remote.ssh_port.is_none() {
+        remote.ssh_port = Some(DEFAULT_SSH_PORT);
+        need_save = true;
+    }
+
+    if args.install_service && remote.service_name.is_none() {
+        remote.service_name = Some(bin_name.clone());
+        need_save = true;
+    }
+
+    let remote_name = remote.name.clone();
+    let target_arch = remote.arch.clone().unwrap_or_else(|| DEFAULT_ARCH.into());
+    let target_name = remote.host.clone().unwrap();
+    let target_user = remote.user.clone().unwrap();
+    let target_dest = remote
+        .dest
+        .clone()
+        .unwrap_or_else(|| "/home/raspberry/bin".into());
+    let ssh_port = remote.ssh_port.unwrap_or(DEFAULT_SSH_PORT);
+    let service_name = remote.service_name.clone().unwrap_or_else(|| bin_name.clone());
+    let service_args = remote.service_args.clone().unwrap_or_default();
+    let use_native = args.native_ssh || remote.transport == Some(TransportKind::Native);
+
+    // `--run` and `--install-service` still shell out to the `ssh` binary
+    // directly (see transport.rs's module doc) rather than going through
+    // the selected `Transport`, so pick a clear error over a confusing
+    // "Failed to launch remote process over ssh" panic on hosts that chose
+    // the native transport precisely because they don't have one.
+    if use_native && (args.run || args.install_service) {
+        panic!(
+            "--run and --install-service shell out to the `ssh` binary directly and don't \
+             support the native transport yet; rerun without --native-ssh/transport=native, \
+             or without --run/--install-service."
+        );
+    }
+
+    if config.default.is_none() {
+        config.default = Some(remote_name);
         need_save = true;
     }
 
@@ -66,55 +279,293 @@ fn main() {
         save_config(&config);
     }
 
-    let target_arch = config
-        .target_arch
-        .clone()
-        .unwrap_or_else(|| "aarch64-unknown-linux-gnu".into());
+    let connection_string = format!("{}@{}", target_user, target_name);
 
     build(&target_arch, release_mode);
 
-    let target_name = config.target_name.clone().unwrap();
-    let target_user = config.target_user.clone().unwrap();
-    configure_ssh_key(&target_name, &target_user);
+    // ControlMaster multiplexing only applies to the shell-out transport;
+    // the native backend already holds one session open for its lifetime.
+    // Started only now, right before the first real connection: the master's
+    // `ControlPersist` idle timer starts ticking the moment it comes up, and
+    // `build()` above (an unbounded cross-compile) can easily outlast it if
+    // nothing has attached yet.
+    let control_socket = if args.multiplex && !use_native {
+        let socket = control_socket_path(&target_user, &target_name);
+        start_control_master(&connection_string, &socket, ssh_port);
+        Some(socket)
+    } else {
+        None
+    };
+    let control_socket = control_socket.as_deref();
 
-    let target_dest = config
-        .target_dest
-        .clone()
-        .unwrap_or_else(|| "/home/raspberry/bin".into());
+    let mut transport: Box<dyn Transport> = if use_native {
+        Box::new(NativeTransport::connect(&target_name, &target_user, ssh_port))
+    } else {
+        Box::new(ShellTransport::new(
+            target_name.clone(),
+            target_user.clone(),
+            ssh_port,
+            control_socket.map(Path::to_path_buf),
+        ))
+    };
+    transport.ensure_key();
+    transport.ensure_dir(&target_dest);
 
-    create_remote_directory(&target_name, &target_user, &target_dest);
-    let bin_name = detect_binary_name();
     let profile_dir: &str = if release_mode { "release" } else { "debug" };
     let binary_path = format!("target/{}/{}/{}", target_arch, profile_dir, bin_name);
-    deploy(&binary_path, &target_name, &target_user, &target_dest);
+    let remote_bin_path = format!("{}/{}", target_dest, bin_name);
+    transport.upload_file(Path::new(&binary_path), &remote_bin_path);
 
     println!("Deployment complete.");
+
+    if args.install_service {
+        install_service(
+            &target_name,
+            &target_user,
+            &target_dest,
+            &bin_name,
+            &service_name,
+            &service_args,
+            ssh_port,
+            control_socket,
+        );
+    }
+
+    let exit_code = if args.run {
+        Some(run_remote(
+            &target_name,
+            &target_user,
+            &target_dest,
+            &bin_name,
+            ssh_port,
+            control_socket,
+        ))
+    } else {
+        None
+    };
+
+    if let Some(socket) = control_socket {
+        stop_control_master(&connection_string, socket);
+    }
+
+    if let Some(exit_code) = exit_code {
+        std::process::exit(exit_code);
+    }
 }
 
-fn deploy(host_path: &str, target_name: &str, target_user: &str, target_dest: &str) {
+/// Installs the deployed binary as a systemd user service so it starts on
+/// boot, restarts on failure, and keeps running without an active login.
+fn install_service(
+    target_name: &str,
+    target_user: &str,
+    target_dest: &str,
+    bin_name: &str,
+    service_name: &str,
+    service_args: &str,
+    ssh_port: u16,
+    control_socket: Option<&Path>,
+) {
     let connection_string = format!("{}@{}", target_user, target_name);
-    println!("Uploading to {}:{}...", connection_string, target_dest);
-    let status = Command::new("scp")
-        .arg(&host_path)
-        .arg(format!("{}:{}", connection_string, target_dest))
-        .stdout(Stdio::null())
+    let remote_bin = format!("{}/{}", target_dest, bin_name);
+    let exec_start = if service_args.is_empty() {
+        remote_bin.clone()
+    } else {
+        format!("{} {}", remote_bin, service_args)
+    };
+
+    println!("Installing systemd user service {}...", service_name);
+
+    let unit = format!(
+        "[Unit]\nDescription={name} (deployed by cargo_deploy)\nAfter=network.target\n\n\
+         [Service]\nExecStart={exec_start}\nRestart=on-failure\n\n\
+         [Install]\nWantedBy=default.target\n",
+        name = service_name,
+        exec_start = exec_start,
+    );
+
+    let remote_cmd = format!(
+        "mkdir -p ~/.config/systemd/user && \
+         cat > ~/.config/systemd/user/{name}.service <<'CARGO_DEPLOY_UNIT'\n{unit}CARGO_DEPLOY_UNIT\n\
+         systemctl --user daemon-reload && \
+         systemctl --user enable --now {name}.service && \
+         systemctl --user restart {name}.service",
+        name = service_name,
+        unit = unit,
+    );
+
+    let status = Command::new("ssh")
+        .args(ssh_port_args(ssh_port, "-p"))
+        .args(control_path_args(control_socket))
+        .arg(&connection_string)
+        .arg(remote_cmd)
         .status()
-        .expect("Failed to run SCP file transfer utility");
+        .expect("Failed to run ssh");
 
     if !status.success() {
         panic!(
-            "SCP file transfer failed. Check your connection to {}",
-            target_name
+            "Failed to install systemd user service {} on {}",
+            service_name, target_name
+        );
+    }
+
+    let status = Command::new("ssh")
+        .args(ssh_port_args(ssh_port, "-p"))
+        .args(control_path_args(control_socket))
+        .arg(&connection_string)
+        .arg(format!("loginctl enable-linger {}", target_user))
+        .status()
+        .expect("Failed to run ssh");
+
+    if !status.success() {
+        panic!(
+            "Failed to enable lingering for {}; the service won't survive reboot \
+             without an active login until you run `loginctl enable-linger {}` yourself.",
+            target_user, target_user
         );
     }
 }
 
+/// Fixed-capacity tail of the most recent lines of remote output, so a
+/// long-running `--run` session doesn't grow memory unboundedly.
+struct LineRingBuffer {
+    lines: VecDeque<String>,
+    capacity: usize,
+}
+
+impl LineRingBuffer {
+    fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    fn push_line(&mut self, line: String) {
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    fn lines(&self) -> impl Iterator<Item = &String> {
+        self.lines.iter()
+    }
+}
+
+/// Launches the deployed binary over SSH and streams its output locally
+/// until it exits or the user hits Ctrl-C, in which case the remote process
+/// is asked to stop too so nothing is left running on the Pi.
+fn run_remote(
+    target_name: &str,
+    target_user: &str,
+    target_dest: &str,
+    bin_name: &str,
+    ssh_port: u16,
+    control_socket: Option<&Path>,
+) -> i32 {
+    let connection_string = format!("{}@{}", target_user, target_name);
+    let remote_bin = format!("{}/{}", target_dest, bin_name);
+    println!("Running {} on {}...", remote_bin, connection_string);
+
+    let mut child = Command::new("ssh")
+        .args(ssh_port_args(ssh_port, "-p"))
+        .args(control_path_args(control_socket))
+        .arg(&connection_string)
+        .arg(&remote_bin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to launch remote process over ssh");
+
+    let log = Arc::new(Mutex::new(LineRingBuffer::new(RUN_LOG_CAPACITY)));
+    let stdout = child.stdout.take().expect("child stdout was piped");
+    let stderr = child.stderr.take().expect("child stderr was piped");
+
+    let stdout_thread = thread::spawn({
+        let log = Arc::clone(&log);
+        move || stream_lines(stdout, &log)
+    });
+    let stderr_thread = thread::spawn({
+        let log = Arc::clone(&log);
+        move || stream_lines(stderr, &log)
+    });
+
+    let interrupted = Arc::new(AtomicBool::new(false));
+    {
+        let interrupted = Arc::clone(&interrupted);
+        let connection_string = connection_string.clone();
+        let remote_bin = remote_bin.clone();
+        let control_socket = control_socket.map(Path::to_path_buf);
+        ctrlc::set_handler(move || {
+            interrupted.store(true, Ordering::SeqCst);
+            terminate_remote(
+                &connection_string,
+                &remote_bin,
+                ssh_port,
+                control_socket.as_deref(),
+            );
+        })
+        .expect("Failed to install Ctrl-C handler");
+    }
+
+    let status = child.wait().expect("Failed to wait on remote process");
+    stdout_thread.join().expect("stdout reader thread panicked");
+    stderr_thread.join().expect("stderr reader thread panicked");
+
+    println!("--- last {} lines of remote output ---", RUN_LOG_CAPACITY);
+    for line in log.lock().unwrap().lines() {
+        println!("{}", line);
+    }
+
+    if interrupted.load(Ordering::SeqCst) {
+        130 // conventional exit code for SIGINT
+    } else {
+        status.code().unwrap_or(1)
+    }
+}
+
+fn stream_lines(pipe: impl io::Read, log: &Arc<Mutex<LineRingBuffer>>) {
+    for line in io::BufReader::new(pipe).lines() {
+        let Ok(line) = line else { break };
+        println!("{}", line);
+        log.lock().unwrap().push_line(line);
+    }
+}
+
+fn terminate_remote(
+    connection_string: &str,
+    remote_bin: &str,
+    ssh_port: u16,
+    control_socket: Option<&Path>,
+) {
+    println!("Stopping remote process on exit...");
+    let _ = Command::new("ssh")
+        .args(ssh_port_args(ssh_port, "-p"))
+        .args(control_path_args(control_socket))
+        .arg(connection_string)
+        .arg(format!("pkill -f '{}'", remote_bin))
+        .status();
+}
+
 fn load_or_create_config() -> Config {
     let config_path = Path::new(CONFIG_FILE);
     if config_path.exists() {
         let data =
             fs::read_to_string(config_path).expect(&format!("Failed to read {}", CONFIG_FILE));
-        serde_json::from_str(&data).expect(&format!("Invalid JSON in {}", CONFIG_FILE))
+        match serde_json::from_str::<Config>(&data) {
+            Ok(config) => config,
+            Err(_) => {
+                let legacy: LegacyConfig = serde_json::from_str(&data)
+                    .expect(&format!("Invalid JSON in {}", CONFIG_FILE));
+                println!(
+                    "Migrating {} to the multi-remote profile format...",
+                    CONFIG_FILE
+                );
+                let config = Config::from(legacy);
+                save_config(&config);
+                config
+            }
+        }
     } else {
         let default = Config::default();
         save_config(&default);
@@ -147,66 +598,142 @@ pub fn sanitize_hostname(hostname: &str) -> String {
     sanitized.trim_matches('_').to_string()
 }
 
-fn configure_ssh_key(target_name: &str, target_user: &str) {
-    println!("Checking SSH connectivity...");
+/// Directory holding ControlMaster sockets for opt-in SSH multiplexing.
+fn control_socket_dir() -> PathBuf {
+    let home = std::env::var("HOME")
+        .expect("HOME environment variable not set. Cannot locate '$HOME/.ssh/' on your machine.");
+    Path::new(&home).join(".ssh").join("control")
+}
 
-    let connection_string = format!("{}@{}", target_user, target_name);
-    let test = Command::new("ssh")
-        .arg("-o")
-        .arg("BatchMode=yes")
-        .arg("-o")
-        .arg("ConnectTimeout=5")
-        .arg(&connection_string)
-        .arg("echo connected")
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status();
+/// Path of the ControlMaster socket for a given host, creating the
+/// directory that holds it if needed. Refuses to operate when `$HOME`
+/// isn't set to anything sensible, rather than silently creating the
+/// socket under the current working directory (empty `$HOME`) or in the
+/// filesystem root (`$HOME=/`).
+fn control_socket_path(target_user: &str, target_name: &str) -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_default();
+    if home.is_empty() || home == "/" {
+        panic!("Refusing to create the SSH control socket with $HOME unset, empty, or '/'; check $HOME.");
+    }
+    let dir = control_socket_dir();
+    fs::create_dir_all(&dir).expect(&format!("Failed to create {}", dir.display()));
+    dir.join(format!(
+        "{}@{}",
+        target_user,
+        sanitize_hostname(target_name)
+    ))
+}
 
-    if let Ok(status) = test {
-        if status.success() {
-            return;
-        }
+/// `-o ControlPath=...` arguments to append to an ssh/scp invocation, or
+/// none if multiplexing is disabled.
+pub(crate) fn control_path_args(control_socket: Option<&Path>) -> Vec<String> {
+    match control_socket {
+        Some(socket) => vec!["-o".into(), format!("ControlPath={}", socket.display())],
+        None => Vec::new(),
     }
+}
 
-    println!("No SSH key configured for {}.", connection_string);
-    let home = std::env::var("HOME")
-        .expect("HOME environment variable not set. Cannot locate '$HOME/.ssh/' on your machine.");
+/// `-p`/`-P` port arguments for an ssh/scp invocation, omitted entirely
+/// when the port is the default so existing command lines stay unchanged.
+pub(crate) fn ssh_port_args(port: u16, flag: &str) -> Vec<String> {
+    if port == DEFAULT_SSH_PORT {
+        Vec::new()
+    } else {
+        vec![flag.to_string(), port.to_string()]
+    }
+}
 
-    let key_path = format!(
-        "{}/.ssh/id_ed25519_{}_{}",
-        home,
-        target_user,
-        sanitize_hostname(&target_name)
-    );
+/// The `ssh ...` command line rsync should use as its remote shell via `-e`,
+/// carrying the same port and ControlMaster socket as every other step.
+pub(crate) fn ssh_transport_command(port: u16, control_socket: Option<&Path>) -> String {
+    let mut parts = vec!["ssh".to_string()];
+    parts.extend(ssh_port_args(port, "-p"));
+    parts.extend(control_path_args(control_socket));
+    parts.join(" ")
+}
 
-    if !Path::new(&key_path).exists() {
-        println!("No SSH key found on your machine. Generating one...");
+/// Whether `name` is runnable on this machine.
+pub(crate) fn command_exists(name: &str) -> bool {
+    Command::new(name)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
 
-        let comment = format!(
-            "Key generated by {}, Version: {}",
-            env!("CARGO_PKG_NAME"),
-            env!("CARGO_PKG_VERSION")
-        );
-        let status = Command::new("ssh-keygen")
-            .args(["-t", "ed25519", "-f", &key_path, "-N", "", "-C", &comment])
-            .status()
-            .expect("Failed to generate SSH key");
+/// Whether `name` is on the remote's PATH.
+pub(crate) fn remote_command_exists(
+    connection_string: &str,
+    ssh_port: u16,
+    control_socket: Option<&Path>,
+    name: &str,
+) -> bool {
+    let status = Command::new("ssh")
+        .args(ssh_port_args(ssh_port, "-p"))
+        .args(control_path_args(control_socket))
+        .arg(connection_string)
+        .arg(format!("command -v {}", name))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    matches!(status, Ok(status) if status.success())
+}
 
-        if !status.success() {
-            panic!("SSH key generation failed");
-        }
+/// Starts a background SSH ControlMaster for `connection_string`, or
+/// reuses one that's already listening on `socket` for this host.
+fn start_control_master(connection_string: &str, socket: &Path, ssh_port: u16) {
+    let already_running = Command::new("ssh")
+        .args(["-O", "check", "-S"])
+        .arg(socket)
+        .arg(connection_string)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    if matches!(already_running, Ok(status) if status.success()) {
+        println!("Reusing existing SSH control master at {}", socket.display());
+        return;
     }
 
-    let status = Command::new("ssh-copy-id")
-        .args(["-i", &key_path, &connection_string])
+    println!("Starting SSH control master at {}...", socket.display());
+    let status = Command::new("ssh")
+        .arg("-M")
+        .arg("-N")
+        .arg("-f")
+        .args(["-S", &socket.to_string_lossy()])
+        .args(ssh_port_args(ssh_port, "-p"))
+        .arg("-o")
+        .arg(format!("ControlPersist={}", CONTROL_PERSIST_SECS))
+        .arg(connection_string)
         .status()
-        .expect("Failed to run ssh-copy-id");
+        .expect("Failed to start SSH control master");
 
     if !status.success() {
-        panic!("ssh-copy-id failed");
+        panic!(
+            "Failed to start an SSH control master for {}. If a stale master is \
+             already running for this host, remove it with `ssh -O exit -S {} {}`.",
+            connection_string,
+            socket.display(),
+            connection_string
+        );
     }
 }
 
+/// Tears down the ControlMaster started by `start_control_master`, if any.
+fn stop_control_master(connection_string: &str, socket: &Path) {
+    if !socket.exists() {
+        return;
+    }
+    let _ = Command::new("ssh")
+        .arg("-O")
+        .arg("exit")
+        .args(["-S", &socket.to_string_lossy()])
+        .arg(connection_string)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
+
 fn build(target_arch: &str, release: bool) {
     println!(
         "Building ({}) for {}...",
@@ -243,16 +770,3 @@ fn detect_binary_name() -> String {
         .clone()
 }
 
-fn create_remote_directory(target_name: &str, target_user: &str, target_dest: &str) {
-    let connection_string = format!("{}@{}", target_user, target_name);
-
-    let status = Command::new("ssh")
-        .arg(connection_string)
-        .arg(format!("mkdir -p {}", target_dest))
-        .status()
-        .expect("Failed to run ssh");
-
-    if !status.success() {
-        panic!("Failed to create remote directory");
-    }
-}
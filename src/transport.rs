@@ -0,0 +1,423 @@
+//! Pluggable ways to talk to the remote device during a deploy.
+//!
+//! [`ShellTransport`] shells out to the system's `ssh`/`scp`/`rsync`/
+//! `ssh-keygen`/`ssh-copy-id` binaries, the same as the rest of this tool
+//! always has. [`NativeTransport`] does the equivalent over an in-process
+//! SSH2 session, for hosts where those binaries aren't on PATH; it still
+//! runs `ssh-keygen` when no local key pair exists yet. Only the
+//! connect/upload/mkdir/authorize steps go through a `Transport` —
+//! `--run` and `--install-service` shell out to `ssh` directly and are
+//! rejected with the native transport, and `--multiplex` only applies to
+//! `ShellTransport`.
+
+use ssh2::{CheckResult, FileStat, KnownHostFileKind, Session};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Resolves the caller's home directory across platforms. `$HOME` covers
+/// Unix and Windows shells with a POSIX layer (Git Bash, WSL); a stock
+/// Windows cmd/PowerShell session never sets it and uses `%USERPROFILE%`
+/// instead, which is exactly the no-`ssh`-binary case `NativeTransport`
+/// targets.
+fn home_dir() -> String {
+    std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .expect("Neither HOME nor USERPROFILE is set. Cannot locate your SSH directory.")
+}
+
+/// How deployment talks to the remote device.
+pub trait Transport {
+    /// Runs `command` on the remote, returning whether it exited zero.
+    fn run_command(&self, command: &str) -> bool;
+    /// Copies the local file at `local_path` to the exact path `remote_path`.
+    fn upload_file(&self, local_path: &Path, remote_path: &str);
+    /// Creates `remote_dir` (and any missing parents) if it doesn't exist.
+    fn ensure_dir(&self, remote_dir: &str);
+    /// Makes sure future connections don't need an interactive password.
+    fn ensure_key(&mut self);
+}
+
+/// The original transport: shells out to the `ssh` family of binaries.
+pub struct ShellTransport {
+    target_name: String,
+    target_user: String,
+    ssh_port: u16,
+    control_socket: Option<PathBuf>,
+    key_path: PathBuf,
+}
+
+impl ShellTransport {
+    pub fn new(
+        target_name: String,
+        target_user: String,
+        ssh_port: u16,
+        control_socket: Option<PathBuf>,
+    ) -> Self {
+        let home = std::env::var("HOME").expect(
+            "HOME environment variable not set. Cannot locate '$HOME/.ssh/' on your machine.",
+        );
+        let key_path = PathBuf::from(format!(
+            "{}/.ssh/id_ed25519_{}_{}",
+            home,
+            target_user,
+            crate::sanitize_hostname(&target_name)
+        ));
+        Self {
+            target_name,
+            target_user,
+            ssh_port,
+            control_socket,
+            key_path,
+        }
+    }
+
+    fn connection_string(&self) -> String {
+        format!("{}@{}", self.target_user, self.target_name)
+    }
+}
+
+impl Transport for ShellTransport {
+    fn run_command(&self, command: &str) -> bool {
+        Command::new("ssh")
+            .args(crate::ssh_port_args(self.ssh_port, "-p"))
+            .args(crate::control_path_args(self.control_socket.as_deref()))
+            .arg(self.connection_string())
+            .arg(command)
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false)
+    }
+
+    fn upload_file(&self, local_path: &Path, remote_path: &str) {
+        let remote = format!("{}:{}", self.connection_string(), remote_path);
+        println!("Uploading to {}...", remote);
+
+        let use_rsync = crate::command_exists("rsync")
+            && crate::remote_command_exists(
+                &self.connection_string(),
+                self.ssh_port,
+                self.control_socket.as_deref(),
+                "rsync",
+            );
+
+        let status = if use_rsync {
+            Command::new("rsync")
+                .args(["-z", "--partial", "--inplace"])
+                .arg("-e")
+                .arg(crate::ssh_transport_command(
+                    self.ssh_port,
+                    self.control_socket.as_deref(),
+                ))
+                .arg(local_path)
+                .arg(&remote)
+                .stdout(Stdio::null())
+                .status()
+                .expect("Failed to run rsync file transfer")
+        } else {
+            Command::new("scp")
+                .args(crate::control_path_args(self.control_socket.as_deref()))
+                .args(crate::ssh_port_args(self.ssh_port, "-P"))
+                .arg(local_path)
+                .arg(&remote)
+                .stdout(Stdio::null())
+                .status()
+                .expect("Failed to run SCP file transfer utility")
+        };
+
+        if !status.success() {
+            panic!(
+                "File transfer failed. Check your connection to {}",
+                self.target_name
+            );
+        }
+    }
+
+    fn ensure_dir(&self, remote_dir: &str) {
+        if !self.run_command(&format!("mkdir -p {}", remote_dir)) {
+            panic!("Failed to create remote directory {}", remote_dir);
+        }
+    }
+
+    fn ensure_key(&mut self) {
+        println!("Checking SSH connectivity...");
+        let test = Command::new("ssh")
+            .arg("-o")
+            .arg("BatchMode=yes")
+            .arg("-o")
+            .arg("ConnectTimeout=5")
+            .args(crate::ssh_port_args(self.ssh_port, "-p"))
+            .args(crate::control_path_args(self.control_socket.as_deref()))
+            .arg(self.connection_string())
+            .arg("echo connected")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+
+        if let Ok(status) = test {
+            if status.success() {
+                return;
+            }
+        }
+
+        println!("No SSH key configured for {}.", self.connection_string());
+
+        if !self.key_path.exists() {
+            println!("No SSH key found on your machine. Generating one...");
+
+            let comment = format!(
+                "Key generated by {}, Version: {}",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION")
+            );
+            let status = Command::new("ssh-keygen")
+                .args([
+                    "-t",
+                    "ed25519",
+                    "-f",
+                    &self.key_path.to_string_lossy(),
+                    "-N",
+                    "",
+                    "-C",
+                    &comment,
+                ])
+                .status()
+                .expect("Failed to generate SSH key");
+
+            if !status.success() {
+                panic!("SSH key generation failed");
+            }
+        }
+
+        let status = Command::new("ssh-copy-id")
+            .args(["-i", &self.key_path.to_string_lossy()])
+            .args(crate::ssh_port_args(self.ssh_port, "-p"))
+            .arg(self.connection_string())
+            .status()
+            .expect("Failed to run ssh-copy-id");
+
+        if !status.success() {
+            panic!("ssh-copy-id failed");
+        }
+    }
+}
+
+/// An in-process transport backed by `ssh2`, for hosts with no `ssh`/`scp`
+/// binaries on PATH (minimal images, Windows without OpenSSH installed).
+pub struct NativeTransport {
+    session: Session,
+    target_user: String,
+    key_path: PathBuf,
+}
+
+impl NativeTransport {
+    /// Opens and authenticates one SSH session, reused for every later call.
+    /// Tries the local key pair first and falls back to an interactive
+    /// password prompt, same as `ShellTransport` falls back to `ssh-copy-id`
+    /// the first time it talks to a new host.
+    pub fn connect(target_name: &str, target_user: &str, ssh_port: u16) -> Self {
+        let home = home_dir();
+        let key_path = PathBuf::from(format!(
+            "{}/.ssh/id_ed25519_{}_{}",
+            home,
+            target_user,
+            crate::sanitize_hostname(target_name)
+        ));
+
+        let addr = format!("{}:{}", target_name, ssh_port);
+        let tcp = TcpStream::connect(&addr)
+            .unwrap_or_else(|err| panic!("Failed to connect to {}: {}", addr, err));
+        let mut session = Session::new().expect("Failed to create SSH session");
+        session.set_tcp_stream(tcp);
+        session.handshake().expect("SSH handshake failed");
+
+        Self::verify_host_key(&session, &home, target_name, ssh_port);
+
+        let authenticated = key_path.exists()
+            && session
+                .userauth_pubkey_file(target_user, None, &key_path, None)
+                .is_ok();
+
+        if !authenticated {
+            println!(
+                "No usable key at {}. Enter the password for {}@{} to connect once:",
+                key_path.display(),
+                target_user,
+                target_name
+            );
+            print!("Password: ");
+            std::io::stdout().flush().unwrap();
+            let password = rpassword::read_password().expect("Failed to read password");
+            session
+                .userauth_password(target_user, password.trim())
+                .expect("Password authentication failed");
+        }
+
+        Self {
+            session,
+            target_user: target_user.to_string(),
+            key_path,
+        }
+    }
+
+    /// Trust-on-first-use host key check against `~/.ssh/known_hosts`, the
+    /// same protection `ShellTransport` gets for free from the system `ssh`
+    /// client. A known host whose key no longer matches aborts the
+    /// connection instead of silently authenticating against it.
+    fn verify_host_key(session: &Session, home: &str, target_name: &str, ssh_port: u16) {
+        let known_hosts_path = PathBuf::from(format!("{}/.ssh/known_hosts", home));
+        let mut known_hosts = session.known_hosts().expect("Failed to init known_hosts");
+        known_hosts
+            .read_file(&known_hosts_path, KnownHostFileKind::OpenSSH)
+            .ok();
+
+        let (key, key_type) = session.host_key().expect("Failed to read remote host key");
+        match known_hosts.check_port(target_name, ssh_port, key) {
+            CheckResult::Match => {}
+            CheckResult::NotFound => {
+                println!(
+                    "Warning: permanently added '{}' to the list of known hosts.",
+                    target_name
+                );
+                known_hosts
+                    .add(target_name, key, target_name, key_type.into())
+                    .expect("Failed to record host key");
+                if let Some(parent) = known_hosts_path.parent() {
+                    std::fs::create_dir_all(parent)
+                        .expect("Failed to create local .ssh directory");
+                }
+                known_hosts
+                    .write_file(&known_hosts_path, KnownHostFileKind::OpenSSH)
+                    .expect("Failed to write known_hosts");
+            }
+            CheckResult::Mismatch => {
+                panic!(
+                    "REMOTE HOST IDENTIFICATION HAS CHANGED for {}! Someone could be \
+                     eavesdropping on this connection (man-in-the-middle attack), or the \
+                     host key has just changed. Refusing to connect.",
+                    target_name
+                );
+            }
+            CheckResult::Failure => panic!("Failed to check {} against known_hosts", target_name),
+        }
+    }
+}
+
+impl Transport for NativeTransport {
+    fn run_command(&self, command: &str) -> bool {
+        let mut channel = self
+            .session
+            .channel_session()
+            .expect("Failed to open SSH channel");
+        channel.exec(command).expect("Failed to exec remote command");
+        channel.wait_close().ok();
+        channel.exit_status().unwrap_or(-1) == 0
+    }
+
+    fn upload_file(&self, local_path: &Path, remote_path: &str) {
+        println!("Uploading to {} over SFTP...", remote_path);
+        let mut local = File::open(local_path)
+            .unwrap_or_else(|err| panic!("Failed to open {}: {}", local_path.display(), err));
+
+        let sftp = self.session.sftp().expect("Failed to start SFTP subsystem");
+        let mut remote = sftp
+            .create(Path::new(remote_path))
+            .expect("Failed to open remote file for writing");
+        std::io::copy(&mut local, &mut remote).expect("Failed to stream file over SFTP");
+        remote
+            .setstat(FileStat {
+                size: None,
+                uid: None,
+                gid: None,
+                perm: Some(0o755),
+                atime: None,
+                mtime: None,
+            })
+            .expect("Failed to mark remote file executable");
+    }
+
+    fn ensure_dir(&self, remote_dir: &str) {
+        let sftp = self.session.sftp().expect("Failed to start SFTP subsystem");
+
+        // Mirror ShellTransport's `mkdir -p`: walk from the root, creating
+        // each missing path component so a multi-level `dest` doesn't panic
+        // on the first segment sftp.mkdir can't create in one shot.
+        let mut path = PathBuf::new();
+        for component in Path::new(remote_dir).components() {
+            path.push(component);
+            if sftp.stat(&path).is_err() {
+                sftp.mkdir(&path, 0o755)
+                    .unwrap_or_else(|err| panic!("Failed to create remote directory {}: {}", path.display(), err));
+            }
+        }
+    }
+
+    fn ensure_key(&mut self) {
+        if !self.key_path.exists() {
+            println!("No SSH key found on your machine. Generating one...");
+            let status = std::process::Command::new("ssh-keygen")
+                .args([
+                    "-t",
+                    "ed25519",
+                    "-f",
+                    &self.key_path.to_string_lossy(),
+                    "-N",
+                    "",
+                ])
+                .status()
+                .expect("Failed to generate SSH key");
+            if !status.success() {
+                panic!("SSH key generation failed");
+            }
+        }
+
+        let mut public_key = String::new();
+        File::open(self.key_path.with_extension("pub"))
+            .and_then(|mut file| file.read_to_string(&mut public_key))
+            .expect("Failed to read local public key");
+        let public_key = public_key.trim();
+
+        let sftp = self.session.sftp().expect("Failed to start SFTP subsystem");
+        let ssh_dir = Path::new(".ssh");
+        let authorized_keys = Path::new(".ssh/authorized_keys");
+
+        // On a freshly imaged device (where key auth failed and the key has
+        // to be published), `~/.ssh` commonly doesn't exist yet.
+        if sftp.stat(ssh_dir).is_err() {
+            match sftp.mkdir(ssh_dir, 0o700) {
+                Ok(()) => {}
+                Err(err) if sftp.stat(ssh_dir).is_ok() => {
+                    let _ = err;
+                }
+                Err(err) => panic!("Failed to create remote .ssh directory: {}", err),
+            }
+        }
+
+        let mut existing = String::new();
+        if let Ok(mut file) = sftp.open(authorized_keys) {
+            file.read_to_string(&mut existing).ok();
+        }
+
+        if existing.lines().any(|line| line.trim() == public_key) {
+            return;
+        }
+
+        println!(
+            "Publishing local public key to {}'s authorized_keys...",
+            self.target_user
+        );
+        if !existing.is_empty() && !existing.ends_with('\n') {
+            existing.push('\n');
+        }
+        existing.push_str(public_key);
+        existing.push('\n');
+
+        let mut file = sftp
+            .create(authorized_keys)
+            .expect("Failed to open remote authorized_keys for writing");
+        file.write_all(existing.as_bytes())
+            .expect("Failed to write remote authorized_keys");
+    }
+}